@@ -1,7 +1,10 @@
-use std::ops::{Index, IndexMut};
+use core::ops::{Index, IndexMut};
 
-use crate::{Handle, SparseHandleMap};
+use alloc::vec::Vec;
 
+use crate::{error::BadHandle, Handle, SparseHandleMap};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DenseHandleMap<T> {
     link: SparseHandleMap<usize>,
@@ -16,7 +19,10 @@ impl<T> Default for DenseHandleMap<T> {
 
 impl<T> IndexMut<Handle<T>> for DenseHandleMap<T> {
     fn index_mut(&mut self, handle: Handle<T>) -> &mut Self::Output {
-        self.get_mut(handle).expect("valid handle")
+        match self.try_get_mut(handle) {
+            Ok(value) => value,
+            Err(err) => panic!("{err}"),
+        }
     }
 }
 
@@ -24,7 +30,10 @@ impl<T> Index<Handle<T>> for DenseHandleMap<T> {
     type Output = T;
 
     fn index(&self, handle: Handle<T>) -> &Self::Output {
-        self.get(handle).expect("valid handle")
+        match self.try_get(handle) {
+            Ok(value) => value,
+            Err(err) => panic!("{err}"),
+        }
     }
 }
 
@@ -67,6 +76,55 @@ impl<T> DenseHandleMap<T> {
         Some(&mut self.values[index].1)
     }
 
+    /// Returns a reference to the value associated with `handle`.
+    ///
+    /// Returns a [`BadHandle`] describing why the lookup failed instead of `None`.
+    pub fn try_get(&self, handle: Handle<T>) -> Result<&T, BadHandle> {
+        let index = *self
+            .link
+            .try_get(handle.cast())
+            .map_err(|err| BadHandle::new::<T>(err.index, err.reason))?;
+        Ok(&self.values[index].1)
+    }
+
+    /// Returns a mutable reference to the value associated with `handle`.
+    ///
+    /// Returns a [`BadHandle`] describing why the lookup failed instead of `None`.
+    pub fn try_get_mut(&mut self, handle: Handle<T>) -> Result<&mut T, BadHandle> {
+        let index = *self
+            .link
+            .try_get(handle.cast())
+            .map_err(|err| BadHandle::new::<T>(err.index, err.reason))?;
+        Ok(&mut self.values[index].1)
+    }
+
+    /// Returns mutable references to the values associated with each of `handles`.
+    ///
+    /// Returns `None` if any handle is invalid, or if two handles resolve to
+    /// the same slot.
+    pub fn get_disjoint_mut<const N: usize>(
+        &mut self,
+        handles: [Handle<T>; N],
+    ) -> Option<[&mut T; N]> {
+        let mut indices = [0; N];
+        for (slot, handle) in indices.iter_mut().zip(handles) {
+            *slot = *self.link.get(handle.cast())?;
+        }
+        for i in 0..N {
+            if indices[i + 1..].contains(&indices[i]) {
+                return None;
+            }
+        }
+
+        // SAFETY: every index in `indices` was resolved through `link`, so it
+        // is in-bounds for `self.values`, and the loop above confirmed they
+        // are pairwise distinct, so the `N` mutable references below never alias.
+        let ptr = self.values.as_mut_ptr();
+        Some(core::array::from_fn(|i| unsafe {
+            &mut (*ptr.add(indices[i])).1
+        }))
+    }
+
     /// Inserts `value` into the map, returning a [`Handle`] to its location.
     ///
     /// # Panics
@@ -134,7 +192,7 @@ impl<T> DenseHandleMap<T> {
 
 /// An iterator that yields all handles and value refrences in a [`DenseHandleMap`].
 pub struct Iter<'a, T> {
-    inner: std::slice::Iter<'a, (Handle<T>, T)>,
+    inner: core::slice::Iter<'a, (Handle<T>, T)>,
 }
 
 impl<'a, T> Iterator for Iter<'a, T> {
@@ -147,7 +205,7 @@ impl<'a, T> Iterator for Iter<'a, T> {
 
 /// An iterator that yields all handles and mutable value refrences in a [`DenseHandleMap`].
 pub struct IterMut<'a, T> {
-    inner: std::slice::IterMut<'a, (Handle<T>, T)>,
+    inner: core::slice::IterMut<'a, (Handle<T>, T)>,
 }
 
 impl<'a, T> Iterator for IterMut<'a, T> {
@@ -160,7 +218,7 @@ impl<'a, T> Iterator for IterMut<'a, T> {
 
 /// An iterator that yields all handles in a [`DenseHandleMap`].
 pub struct Handles<'a, T> {
-    inner: std::slice::Iter<'a, (Handle<T>, T)>,
+    inner: core::slice::Iter<'a, (Handle<T>, T)>,
 }
 
 impl<'a, T> Iterator for Handles<'a, T> {
@@ -173,7 +231,7 @@ impl<'a, T> Iterator for Handles<'a, T> {
 
 /// An iterator that yields all value refrences in a [`DenseHandleMap`].
 pub struct Values<'a, T> {
-    inner: std::slice::Iter<'a, (Handle<T>, T)>,
+    inner: core::slice::Iter<'a, (Handle<T>, T)>,
 }
 
 impl<'a, T> Iterator for Values<'a, T> {
@@ -186,7 +244,7 @@ impl<'a, T> Iterator for Values<'a, T> {
 
 /// An iterator that yields all mutable value refrences in a [`DenseHandleMap`].
 pub struct ValuesMut<'a, T> {
-    inner: std::slice::IterMut<'a, (Handle<T>, T)>,
+    inner: core::slice::IterMut<'a, (Handle<T>, T)>,
 }
 
 impl<'a, T> Iterator for ValuesMut<'a, T> {
@@ -208,6 +266,14 @@ mod test {
         assert_eq!(map.get(handle), Some(&42));
     }
 
+    #[test]
+    pub fn new_is_const() {
+        // `new` must stay a `const fn`, and the map must stay `Sync`, so a
+        // `static` can hold one directly.
+        static MAP: DenseHandleMap<i32> = DenseHandleMap::new();
+        assert!(MAP.is_empty());
+    }
+
     #[test]
     pub fn remove() {
         let mut map = DenseHandleMap::new();
@@ -242,4 +308,38 @@ mod test {
         map.remove(handle);
         assert_eq!(map.len(), 1);
     }
+
+    #[test]
+    pub fn try_get() {
+        use crate::error::BadHandleReason;
+
+        let mut map = DenseHandleMap::new();
+        let handle = map.insert(42);
+        assert_eq!(map.try_get(handle), Ok(&42));
+
+        map.remove(handle);
+        assert_eq!(
+            map.try_get(handle).unwrap_err().reason,
+            BadHandleReason::StaleGeneration
+        );
+        assert_eq!(
+            map.try_get(handle).unwrap_err().kind,
+            core::any::type_name::<i32>()
+        );
+    }
+
+    #[test]
+    pub fn get_disjoint_mut() {
+        let mut map = DenseHandleMap::new();
+        let a = map.insert(1);
+        let b = map.insert(2);
+
+        let [a_ref, b_ref] = map.get_disjoint_mut([a, b]).unwrap();
+        *a_ref += 10;
+        *b_ref += 20;
+        assert_eq!(map.get(a), Some(&11));
+        assert_eq!(map.get(b), Some(&22));
+
+        assert_eq!(map.get_disjoint_mut([a, a]), None);
+    }
 }
@@ -0,0 +1,59 @@
+use core::fmt::{self, Display};
+
+/// The reason a [`BadHandle`] lookup failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadHandleReason {
+    /// The handle's index was out of bounds for the map.
+    OutOfBounds,
+    /// The index was in bounds, but no live value is stored there anymore
+    /// (the slot was removed, or reused and stamped with a new generation).
+    StaleGeneration,
+    /// The handle was minted by a different map.
+    ForeignMap,
+}
+
+/// The error returned by the fallible accessors on [`SparseHandleMap`](crate::SparseHandleMap)
+/// and [`DenseHandleMap`](crate::DenseHandleMap) when a [`Handle`](crate::Handle) does not
+/// resolve to a live value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadHandle {
+    /// The type name of the value the handle was supposed to resolve to.
+    pub kind: &'static str,
+    /// The handle's index.
+    pub index: usize,
+    /// Why the lookup failed.
+    pub reason: BadHandleReason,
+}
+
+impl BadHandle {
+    pub(crate) fn new<T: ?Sized>(index: usize, reason: BadHandleReason) -> Self {
+        Self {
+            kind: core::any::type_name::<T>(),
+            index,
+            reason,
+        }
+    }
+}
+
+impl Display for BadHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            BadHandleReason::OutOfBounds => {
+                write!(f, "index {} out of bounds for `{}`", self.index, self.kind)
+            }
+            BadHandleReason::StaleGeneration => write!(
+                f,
+                "handle at index {} of `{}` no longer refers to a live value",
+                self.index, self.kind
+            ),
+            BadHandleReason::ForeignMap => write!(
+                f,
+                "handle at index {} of `{}` was minted by a different map",
+                self.index, self.kind
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BadHandle {}
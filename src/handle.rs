@@ -1,15 +1,31 @@
-use std::{
+use core::{
     fmt::{Debug, Display},
     hash::Hash,
     marker::PhantomData,
+    num::NonZeroU64,
 };
 
 pub struct Handle<T: ?Sized> {
     _type: PhantomData<fn() -> T>,
-    id: u64,
+    // stored as `raw + 1` so the all-zero bit pattern is never a valid
+    // handle, giving `Option<Handle<T>>` the same size as `Handle<T>`.
+    id: NonZeroU64,
 }
 
 impl<T: ?Sized> Handle<T> {
+    /// The sentinel value for "no handle".
+    ///
+    /// Because `Handle<T>` reserves the all-zero bit pattern as its niche,
+    /// `Handle::<T>::NULL` and `None::<Handle<T>>` are one and the same, and
+    /// `size_of::<Option<Handle<T>>>() == size_of::<Handle<T>>()`.
+    pub const NULL: Option<Self> = None;
+
+    /// Returns `true` if `handle` is [`Handle::NULL`].
+    #[inline]
+    pub fn is_null(handle: Option<Self>) -> bool {
+        handle.is_none()
+    }
+
     #[inline]
     pub fn cast<T2>(self) -> Handle<T2> {
         Handle {
@@ -18,30 +34,50 @@ impl<T: ?Sized> Handle<T> {
         }
     }
 
+    /// Converts `id` into a handle without panicking, for callers (like our
+    /// `Deserialize` impl) that need to reject [`u64::MAX`](Self::from_raw#panics)
+    /// rather than panic on it.
     #[inline]
-    pub fn from_raw(id: u64) -> Self {
-        Self {
+    fn try_from_raw(id: u64) -> Option<Self> {
+        Some(Self {
             _type: PhantomData,
-            id,
-        }
+            id: NonZeroU64::new(id.wrapping_add(1))?,
+        })
+    }
+
+    /// Converts a raw id, as returned by [`raw`](Self::raw), back into a handle.
+    ///
+    /// # Panics
+    /// Panics if `id == u64::MAX`. The niche optimization that makes
+    /// `Option<Handle<T>>` the same size as `Handle<T>` (see
+    /// [`NULL`](Self::NULL)) gives up exactly one bit pattern; `u64::MAX` is
+    /// the value reserved, so that the far more useful all-zero raw id keeps
+    /// round-tripping instead. No handle minted by a map's `insert` can reach
+    /// this value short of maxing out a slot's index, generation, *and* the
+    /// map's identity all at once.
+    #[inline]
+    pub fn from_raw(id: u64) -> Self {
+        Self::try_from_raw(id).expect("raw id u64::MAX is reserved and cannot be represented")
     }
 
+    /// Converts `meta` and `index` into a handle.
+    ///
+    /// # Panics
+    /// Panics if `meta == u32::MAX && index == u32::MAX`; see
+    /// [`from_raw`](Self::from_raw#panics).
     #[inline]
     pub fn from_parts(meta: u32, index: u32) -> Self {
-        Self {
-            _type: PhantomData,
-            id: ((meta as u64) << u32::BITS) + index as u64,
-        }
+        Self::from_raw(((meta as u64) << u32::BITS) + index as u64)
     }
 
     #[inline]
     pub fn raw(self) -> u64 {
-        self.id
+        self.id.get() - 1
     }
 
     #[inline]
     pub fn index(self) -> u32 {
-        self.id as u32
+        self.raw() as u32
     }
 
     #[inline]
@@ -51,21 +87,43 @@ impl<T: ?Sized> Handle<T> {
 
     #[inline]
     pub fn meta(self) -> u32 {
-        (self.id >> u32::BITS) as u32
+        (self.raw() >> u32::BITS) as u32
+    }
+
+    /// Returns the high 16 bits of [`meta`](Self::meta).
+    ///
+    /// For handles minted by [`SparseHandleMap`](crate::SparseHandleMap) or
+    /// [`DenseHandleMap`](crate::DenseHandleMap), this identifies the map the
+    /// handle was minted from, so a map of the same type with a different
+    /// identity rejects the handle. Identities are 16 bits, so they are not
+    /// unique across more than 65535 maps.
+    #[inline]
+    pub fn map_id(self) -> u16 {
+        (self.meta() >> u16::BITS) as u16
+    }
+
+    /// Returns the low 16 bits of [`meta`](Self::meta).
+    ///
+    /// For handles minted by [`SparseHandleMap`](crate::SparseHandleMap) or
+    /// [`DenseHandleMap`](crate::DenseHandleMap), this is the generation
+    /// counter for the handle's slot, incremented on every reuse.
+    #[inline]
+    pub fn generation(self) -> u16 {
+        self.meta() as u16
     }
 }
 
 impl<T: ?Sized> Debug for Handle<T> {
     #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Handle").field("id", &self.id).finish()
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Handle").field("id", &self.raw()).finish()
     }
 }
 
 impl<T: ?Sized> Display for Handle<T> {
     #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Handle({})", self.id)
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Handle({})", self.raw())
     }
 }
 
@@ -75,7 +133,7 @@ impl<T: ?Sized> Clone for Handle<T> {
     fn clone(&self) -> Self {
         Self {
             _type: PhantomData,
-            id: self.id.clone(),
+            id: self.id,
         }
     }
 }
@@ -90,24 +148,49 @@ impl<T: ?Sized> PartialEq for Handle<T> {
 
 impl<T: ?Sized> Ord for Handle<T> {
     #[inline]
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.id.cmp(&other.id)
     }
 }
 impl<T: ?Sized> PartialOrd for Handle<T> {
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         self.id.partial_cmp(&other.id)
     }
 }
 
 impl<T: ?Sized> Hash for Handle<T> {
     #[inline]
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         self.id.hash(state);
     }
 }
 
+// `Handle<T>` serializes transparently as its raw `u64`, skipping the
+// `PhantomData` marker, so a handle round-trips through any serde format
+// as a plain integer.
+#[cfg(feature = "serde")]
+impl<T: ?Sized> serde::Serialize for Handle<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.raw(), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: ?Sized> serde::Deserialize<'de> for Handle<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = <u64 as serde::Deserialize>::deserialize(deserializer)?;
+        Self::try_from_raw(raw)
+            .ok_or_else(|| serde::de::Error::custom("handle id u64::MAX is reserved"))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -137,4 +220,45 @@ mod test {
         assert_eq!(handle.uindex(), uindex);
         assert_eq!(handle.raw(), raw);
     }
+
+    #[test]
+    #[should_panic(expected = "reserved")]
+    fn from_raw_max_is_reserved() {
+        Handle::<()>::from_raw(u64::MAX);
+    }
+
+    #[test]
+    fn try_from_raw_rejects_reserved_value_without_panicking() {
+        // the fallible path our Deserialize impl uses must reject the
+        // reserved value instead of panicking, unlike `from_raw`.
+        assert!(Handle::<()>::try_from_raw(u64::MAX).is_none());
+        assert!(Handle::<()>::try_from_raw(0).is_some());
+    }
+
+    #[test]
+    fn map_id_and_generation() {
+        let map_id = 12;
+        let generation = 34;
+        let meta = ((map_id as u32) << u16::BITS) | generation as u32;
+        let handle = Handle::<()>::from_parts(meta, 0);
+        assert_eq!(handle.map_id(), map_id);
+        assert_eq!(handle.generation(), generation);
+    }
+
+    #[test]
+    fn zero_is_not_null() {
+        // from_parts(0, 0) must keep round-tripping to the all-zero raw id,
+        // even though the niche reserves the all-zero *internal* pattern.
+        let handle = Handle::<()>::from_parts(0, 0);
+        assert_eq!(handle.raw(), 0);
+        assert!(!Handle::is_null(Some(handle)));
+    }
+
+    #[test]
+    fn niche_optimization() {
+        assert_eq!(
+            core::mem::size_of::<Option<Handle<()>>>(),
+            core::mem::size_of::<Handle<()>>()
+        );
+    }
 }
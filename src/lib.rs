@@ -1,7 +1,25 @@
+//! Generational handle maps.
+//!
+//! Enable the `serde` feature to (de)serialize [`Handle`], [`SparseHandleMap`],
+//! and [`DenseHandleMap`]. A handle serializes transparently as its raw `u64`;
+//! a map serializes its full internal state, so a deserialized map's handles
+//! still validate against handles serialized from the original.
+//!
+//! This crate is `no_std`, relying only on `alloc` (and `hashbrown` for
+//! [`UniqueHandleMap`]'s lookup table). The default-on `std` feature only
+//! gates [`BadHandle`]'s `std::error::Error` impl.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod dense;
+pub mod error;
 pub mod handle;
 pub mod sparse;
+pub mod unique;
 
 pub use dense::DenseHandleMap;
+pub use error::{BadHandle, BadHandleReason};
 pub use handle::Handle;
 pub use sparse::SparseHandleMap;
+pub use unique::UniqueHandleMap;
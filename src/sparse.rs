@@ -1,16 +1,123 @@
-use std::{
-    collections::VecDeque,
+use core::{
+    cmp::Ordering as CmpOrdering,
+    fmt,
+    hash::{Hash, Hasher},
     ops::{Index, IndexMut},
+    sync::atomic::{AtomicU16, Ordering},
 };
 
-use crate::Handle;
+use alloc::{collections::VecDeque, vec::Vec};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use crate::{
+    error::{BadHandle, BadHandleReason},
+    Handle,
+};
+
+/// Global counter used to stamp each [`SparseHandleMap`] with a distinct identity.
+///
+/// Starts at `1` so that `0` is free to mean "not yet materialized" for
+/// [`SparseHandleMap::map_id`].
+static NEXT_MAP_ID: AtomicU16 = AtomicU16::new(1);
+
+/// Packs a map identity and generation counter into a [`Handle`]'s `meta` field.
+fn pack_meta(map_id: u16, generation: u16) -> u32 {
+    ((map_id as u32) << u16::BITS) | generation as u32
+}
+
+/// A sparse, generational handle map.
+///
+/// Every handle minted by a map is stamped with that map's identity (the high
+/// 16 bits of its `meta`), so the generation counter occupying the low 16
+/// bits now wraps after 65536 reuses of a slot instead of `u32::MAX`. This is
+/// an acceptable trade given the existing tolerance for generation ABA.
+///
+/// A map's identity is assigned lazily, from the global counter, the first
+/// time it is needed (minting, looking up, or removing a handle) rather than
+/// in [`new`](Self::new), so `new` can stay a `const fn` — e.g.
+/// `static MAP: SparseHandleMap<T> = SparseHandleMap::new();` keeps working.
+/// This is why the identity is held in an [`AtomicU16`] instead of a plain
+/// `u16`: a `static` must be `Sync`, and resolving the identity only needs
+/// `&self`, so interior mutability is unavoidable either way — an atomic
+/// keeps the map `Sync` (a `Cell` would not), at the cost of `Clone`,
+/// `PartialEq`, `Ord`, `Hash`, and `Debug` needing hand-written impls below
+/// instead of `derive`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SparseHandleMap<T> {
+    /// Identity stamped into every handle minted by this map, so a handle
+    /// from a map with a different identity never resolves here even if
+    /// index/generation collide. Identities are 16 bits and the global
+    /// counter wraps, so they repeat once more than 65535 maps have been
+    /// assigned one; a clone keeps the identity of the map it was cloned
+    /// from. `0` means "not yet assigned"; see [`Self::map_id`].
+    map_id: AtomicU16,
     values: Vec<(Handle<T>, Option<T>)>,
     available: VecDeque<usize>,
 }
 
+impl<T: Clone> Clone for SparseHandleMap<T> {
+    fn clone(&self) -> Self {
+        Self {
+            map_id: AtomicU16::new(self.map_id.load(Ordering::Relaxed)),
+            values: self.values.clone(),
+            available: self.available.clone(),
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for SparseHandleMap<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map_id.load(Ordering::Relaxed) == other.map_id.load(Ordering::Relaxed)
+            && self.values == other.values
+            && self.available == other.available
+    }
+}
+
+impl<T: Eq> Eq for SparseHandleMap<T> {}
+
+impl<T: PartialOrd> PartialOrd for SparseHandleMap<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        match self
+            .map_id
+            .load(Ordering::Relaxed)
+            .partial_cmp(&other.map_id.load(Ordering::Relaxed))
+        {
+            Some(CmpOrdering::Equal) => match self.values.partial_cmp(&other.values) {
+                Some(CmpOrdering::Equal) => self.available.partial_cmp(&other.available),
+                ordering => ordering,
+            },
+            ordering => ordering,
+        }
+    }
+}
+
+impl<T: Ord> Ord for SparseHandleMap<T> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.map_id
+            .load(Ordering::Relaxed)
+            .cmp(&other.map_id.load(Ordering::Relaxed))
+            .then_with(|| self.values.cmp(&other.values))
+            .then_with(|| self.available.cmp(&other.available))
+    }
+}
+
+impl<T: Hash> Hash for SparseHandleMap<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map_id.load(Ordering::Relaxed).hash(state);
+        self.values.hash(state);
+        self.available.hash(state);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SparseHandleMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SparseHandleMap")
+            .field("map_id", &self.map_id.load(Ordering::Relaxed))
+            .field("values", &self.values)
+            .field("available", &self.available)
+            .finish()
+    }
+}
+
 impl<T> Default for SparseHandleMap<T> {
     fn default() -> Self {
         Self::new()
@@ -19,7 +126,10 @@ impl<T> Default for SparseHandleMap<T> {
 
 impl<T> IndexMut<Handle<T>> for SparseHandleMap<T> {
     fn index_mut(&mut self, handle: Handle<T>) -> &mut Self::Output {
-        self.get_mut(handle).expect("valid handle")
+        match self.try_get_mut(handle) {
+            Ok(value) => value,
+            Err(err) => panic!("{err}"),
+        }
     }
 }
 
@@ -27,18 +137,47 @@ impl<T> Index<Handle<T>> for SparseHandleMap<T> {
     type Output = T;
 
     fn index(&self, handle: Handle<T>) -> &Self::Output {
-        self.get(handle).expect("valid handle")
+        match self.try_get(handle) {
+            Ok(value) => value,
+            Err(err) => panic!("{err}"),
+        }
     }
 }
 
 impl<T> SparseHandleMap<T> {
     pub const fn new() -> Self {
         Self {
+            map_id: AtomicU16::new(0),
             values: Vec::new(),
             available: VecDeque::new(),
         }
     }
 
+    /// Returns this map's identity, assigning one from the global counter
+    /// the first time it's needed so [`new`](Self::new) can stay `const`.
+    ///
+    /// A racing pair of callers may both mint an id and lose one to the
+    /// `compare_exchange` below; the loser's id is simply never used. That's
+    /// fine — ids only need to be distinct, not contiguous.
+    fn map_id(&self) -> u16 {
+        let id = self.map_id.load(Ordering::Relaxed);
+        if id != 0 {
+            return id;
+        }
+        let mut new_id = NEXT_MAP_ID.fetch_add(1, Ordering::Relaxed);
+        if new_id == 0 {
+            // the counter wrapped; skip `0`, which means "not yet assigned"
+            new_id = NEXT_MAP_ID.fetch_add(1, Ordering::Relaxed);
+        }
+        match self
+            .map_id
+            .compare_exchange(0, new_id, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => new_id,
+            Err(existing) => existing,
+        }
+    }
+
     /// Returns the number of populated values in the map.
     pub fn len(&self) -> usize {
         self.values.len() - self.available.len()
@@ -56,8 +195,12 @@ impl<T> SparseHandleMap<T> {
 
     /// Returns a reference to the value associated with `handle`.
     ///
-    /// Returns `None` if the value does not exist.
+    /// Returns `None` if the value does not exist, including when `handle`
+    /// was minted by a different map.
     pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        if handle.map_id() != self.map_id() {
+            return None;
+        }
         let (found_handle, option) = self.values.get(handle.uindex())?;
         if &handle != found_handle {
             return None;
@@ -67,8 +210,12 @@ impl<T> SparseHandleMap<T> {
 
     /// Returns a mutable reference to the value associated with `handle`.
     ///
-    /// Returns `None` if the value does not exist.
+    /// Returns `None` if the value does not exist, including when `handle`
+    /// was minted by a different map.
     pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        if handle.map_id() != self.map_id() {
+            return None;
+        }
         let (found_handle, option) = self.values.get_mut(handle.uindex())?;
         if &handle != found_handle {
             return None;
@@ -76,6 +223,85 @@ impl<T> SparseHandleMap<T> {
         option.as_mut()
     }
 
+    /// Returns a reference to the value associated with `handle`.
+    ///
+    /// Returns a [`BadHandle`] describing why the lookup failed instead of `None`.
+    pub fn try_get(&self, handle: Handle<T>) -> Result<&T, BadHandle> {
+        if handle.map_id() != self.map_id() {
+            return Err(BadHandle::new::<T>(
+                handle.uindex(),
+                BadHandleReason::ForeignMap,
+            ));
+        }
+        let (found_handle, option) = self
+            .values
+            .get(handle.uindex())
+            .ok_or_else(|| BadHandle::new::<T>(handle.uindex(), BadHandleReason::OutOfBounds))?;
+        if &handle != found_handle {
+            return Err(BadHandle::new::<T>(
+                handle.uindex(),
+                BadHandleReason::StaleGeneration,
+            ));
+        }
+        option
+            .as_ref()
+            .ok_or_else(|| BadHandle::new::<T>(handle.uindex(), BadHandleReason::StaleGeneration))
+    }
+
+    /// Returns a mutable reference to the value associated with `handle`.
+    ///
+    /// Returns a [`BadHandle`] describing why the lookup failed instead of `None`.
+    pub fn try_get_mut(&mut self, handle: Handle<T>) -> Result<&mut T, BadHandle> {
+        if handle.map_id() != self.map_id() {
+            return Err(BadHandle::new::<T>(
+                handle.uindex(),
+                BadHandleReason::ForeignMap,
+            ));
+        }
+        let (found_handle, option) = self
+            .values
+            .get_mut(handle.uindex())
+            .ok_or_else(|| BadHandle::new::<T>(handle.uindex(), BadHandleReason::OutOfBounds))?;
+        if &handle != found_handle {
+            return Err(BadHandle::new::<T>(
+                handle.uindex(),
+                BadHandleReason::StaleGeneration,
+            ));
+        }
+        option
+            .as_mut()
+            .ok_or_else(|| BadHandle::new::<T>(handle.uindex(), BadHandleReason::StaleGeneration))
+    }
+
+    /// Returns mutable references to the values associated with each of `handles`.
+    ///
+    /// Returns `None` if any handle is invalid, or if two handles resolve to
+    /// the same slot.
+    pub fn get_disjoint_mut<const N: usize>(
+        &mut self,
+        handles: [Handle<T>; N],
+    ) -> Option<[&mut T; N]> {
+        let mut indices = [0; N];
+        for (slot, handle) in indices.iter_mut().zip(handles) {
+            self.get(handle)?;
+            *slot = handle.uindex();
+        }
+        for i in 0..N {
+            if indices[i + 1..].contains(&indices[i]) {
+                return None;
+            }
+        }
+
+        // SAFETY: every index in `indices` was confirmed in-bounds by the
+        // `get` check above, and the loop above confirmed they are pairwise
+        // distinct, so the `N` mutable references below never alias.
+        let ptr = self.values.as_mut_ptr();
+        Some(core::array::from_fn(|i| unsafe {
+            let (_, option) = &mut *ptr.add(indices[i]);
+            option.as_mut().unwrap_unchecked()
+        }))
+    }
+
     /// Predicts the next handle that will be generated.
     ///
     /// This is just an alias for [`predict_handle(0)`](Self::predict_handle).
@@ -91,19 +317,21 @@ impl<T> SparseHandleMap<T> {
     /// This is only accurate for multiple inserts. Once a single removal is made, this prediction can de-sync.
     ///
     /// # Panics
-    /// This function will panic if the predicted capacity exceeds `u32::MAX`
+    /// This function will panic if the predicted capacity exceeds `u32::MAX`,
+    /// or in the extreme edge case described on [`insert`](Self::insert#panics).
     pub fn predict_handle(&self, count: usize) -> Handle<T> {
         // first check if there will be existing handles re-used
         if let Some(index) = self.available.get(count) {
             let (handle, _) = &self.values[*index];
-            return Handle::from_parts(handle.meta().wrapping_add(1), handle.index());
+            let generation = handle.generation().wrapping_add(1);
+            return Handle::from_parts(pack_meta(self.map_id(), generation), handle.index());
         }
 
         // otherwise generate the next new handle
         let new_count = count - self.available.len();
         let new_index = self.values.len() + new_count;
         match new_index <= u32::MAX as usize {
-            true => Handle::from_parts(0, new_index as u32),
+            true => Handle::from_parts(pack_meta(self.map_id(), 0), new_index as u32),
             false => panic!("capacity overflow"),
         }
     }
@@ -111,11 +339,15 @@ impl<T> SparseHandleMap<T> {
     /// Inserts `value` into the map, returning a [`Handle`] to its location.
     ///
     /// # Panics
-    /// Panics if the new capacity exceeds `u32::MAX`
+    /// Panics if the new capacity exceeds `u32::MAX`, or in the extreme edge
+    /// case where this map's identity, the slot's generation, and its index
+    /// are all simultaneously maxed out; see [`Handle::from_raw`](crate::Handle::from_raw#panics).
     pub fn insert(&mut self, value: T) -> Handle<T> {
+        let map_id = self.map_id();
         if let Some(index) = self.available.pop_front() {
             let (handle, option) = &mut self.values[index];
-            *handle = Handle::from_parts(handle.meta().wrapping_add(1), handle.index());
+            let generation = handle.generation().wrapping_add(1);
+            *handle = Handle::from_parts(pack_meta(map_id, generation), handle.index());
             *option = Some(value);
             return *handle;
         }
@@ -124,7 +356,7 @@ impl<T> SparseHandleMap<T> {
         match new_index <= u32::MAX as usize {
             false => panic!("capacity overflow"),
             true => {
-                let handle = Handle::from_parts(0, new_index as u32);
+                let handle = Handle::from_parts(pack_meta(map_id, 0), new_index as u32);
                 self.values.push((handle, Some(value)));
                 handle
             }
@@ -133,8 +365,12 @@ impl<T> SparseHandleMap<T> {
 
     /// Removes the value associated with `handle` from the map.
     ///
-    /// Returns `None` if the value does not exist.
+    /// Returns `None` if the value does not exist, including when `handle`
+    /// was minted by a different map.
     pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        if handle.map_id() != self.map_id() {
+            return None;
+        }
         let (found_handle, option) = self.values.get_mut(handle.uindex())?;
         if &handle != found_handle {
             return None;
@@ -183,7 +419,7 @@ impl<T> SparseHandleMap<T> {
 
 /// An iterator that yields all handles and value refrences in a [`SparseHandleMap`].
 pub struct Iter<'a, T> {
-    inner: std::slice::Iter<'a, (Handle<T>, Option<T>)>,
+    inner: core::slice::Iter<'a, (Handle<T>, Option<T>)>,
 }
 
 impl<'a, T> Iterator for Iter<'a, T> {
@@ -201,7 +437,7 @@ impl<'a, T> Iterator for Iter<'a, T> {
 
 /// An iterator that yields all handles and mutable value refrences in a [`SparseHandleMap`].
 pub struct IterMut<'a, T> {
-    inner: std::slice::IterMut<'a, (Handle<T>, Option<T>)>,
+    inner: core::slice::IterMut<'a, (Handle<T>, Option<T>)>,
 }
 
 impl<'a, T> Iterator for IterMut<'a, T> {
@@ -219,7 +455,7 @@ impl<'a, T> Iterator for IterMut<'a, T> {
 
 /// An iterator that yields all handles in a [`SparseHandleMap`].
 pub struct Handles<'a, T> {
-    inner: std::slice::Iter<'a, (Handle<T>, Option<T>)>,
+    inner: core::slice::Iter<'a, (Handle<T>, Option<T>)>,
 }
 
 impl<'a, T> Iterator for Handles<'a, T> {
@@ -237,7 +473,7 @@ impl<'a, T> Iterator for Handles<'a, T> {
 
 /// An iterator that yields all value refrences in a [`SparseHandleMap`].
 pub struct Values<'a, T> {
-    inner: std::slice::Iter<'a, (Handle<T>, Option<T>)>,
+    inner: core::slice::Iter<'a, (Handle<T>, Option<T>)>,
 }
 
 impl<'a, T> Iterator for Values<'a, T> {
@@ -255,7 +491,7 @@ impl<'a, T> Iterator for Values<'a, T> {
 
 /// An iterator that yields all mutable value refrences in a [`SparseHandleMap`].
 pub struct ValuesMut<'a, T> {
-    inner: std::slice::IterMut<'a, (Handle<T>, Option<T>)>,
+    inner: core::slice::IterMut<'a, (Handle<T>, Option<T>)>,
 }
 
 impl<'a, T> Iterator for ValuesMut<'a, T> {
@@ -282,6 +518,14 @@ mod test {
         assert_eq!(map.get(handle), Some(&42));
     }
 
+    #[test]
+    pub fn new_is_const() {
+        // `new` must stay a `const fn`, and the map must stay `Sync`, so a
+        // `static` can hold one directly.
+        static MAP: SparseHandleMap<i32> = SparseHandleMap::new();
+        assert!(MAP.is_empty());
+    }
+
     #[test]
     pub fn remove() {
         let mut map = SparseHandleMap::new();
@@ -316,4 +560,63 @@ mod test {
         map.remove(handle);
         assert_eq!(map.len(), 1);
     }
+
+    #[test]
+    pub fn foreign_handle() {
+        let mut map_a = SparseHandleMap::new();
+        let mut map_b = SparseHandleMap::new();
+
+        let handle_a = map_a.insert(123);
+        let handle_b = map_b.insert(123);
+        assert_ne!(handle_a.map_id(), handle_b.map_id());
+
+        assert_eq!(map_b.get(handle_a), None);
+        assert_eq!(map_a.get(handle_b), None);
+        assert_eq!(map_b.remove(handle_a), None);
+    }
+
+    #[test]
+    pub fn try_get() {
+        let mut map = SparseHandleMap::new();
+        let other = SparseHandleMap::<i32>::new();
+
+        let handle = map.insert(42);
+        assert_eq!(map.try_get(handle), Ok(&42));
+
+        let stale = map.remove(handle).map(|_| handle).unwrap();
+        map.insert(7);
+        assert_eq!(
+            map.try_get(stale).unwrap_err().reason,
+            BadHandleReason::StaleGeneration
+        );
+
+        let out_of_bounds = Handle::from_parts(
+            ((stale.map_id() as u32) << u16::BITS) | stale.generation() as u32,
+            999,
+        );
+        assert_eq!(
+            map.try_get(out_of_bounds).unwrap_err().reason,
+            BadHandleReason::OutOfBounds
+        );
+
+        assert_eq!(
+            other.try_get(handle).unwrap_err().reason,
+            BadHandleReason::ForeignMap
+        );
+    }
+
+    #[test]
+    pub fn get_disjoint_mut() {
+        let mut map = SparseHandleMap::new();
+        let a = map.insert(1);
+        let b = map.insert(2);
+
+        let [a_ref, b_ref] = map.get_disjoint_mut([a, b]).unwrap();
+        *a_ref += 10;
+        *b_ref += 20;
+        assert_eq!(map.get(a), Some(&11));
+        assert_eq!(map.get(b), Some(&22));
+
+        assert_eq!(map.get_disjoint_mut([a, a]), None);
+    }
 }
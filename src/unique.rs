@@ -0,0 +1,157 @@
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+
+use hashbrown::HashMap;
+
+use crate::{dense, DenseHandleMap, Handle};
+
+/// A minimal FNV-1a hasher, used only to compute [`hash_of`]'s lookup key.
+///
+/// `std`'s `DefaultHasher` isn't available without `std`, so this map rolls
+/// its own rather than require a hashing crate just to fold each value down
+/// to a `u64` key. It's never used as `lookup`'s `BuildHasher` (that's `hashbrown`'s
+/// default), only to fold an arbitrary `T: Hash` down to a `u64` key.
+struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Computes a value's hash the same way regardless of the ambient hasher,
+/// so it can be used as a lookup key independent of any particular `HashMap`.
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = FnvHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A handle map that deduplicates equal values.
+///
+/// Inserting a value equal to one already present returns the *existing*
+/// handle rather than storing a duplicate, making this ideal for interning
+/// strings, types, or other content-addressed data.
+///
+/// Entries are content-addressed, so there is no `remove`: once interned, a
+/// value lives for the lifetime of the map, mirroring naga's `UniqueArena`.
+/// If callers need to free entries, reference-count them externally.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniqueHandleMap<T: Hash + Eq> {
+    values: DenseHandleMap<T>,
+    // maps a value's hash to every handle whose value hashes the same, so
+    // `insert`/`get_handle` can find an existing equal value without storing
+    // `T` a second time.
+    lookup: HashMap<u64, Vec<Handle<T>>>,
+}
+
+impl<T: Hash + Eq> Default for UniqueHandleMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq> core::ops::Index<Handle<T>> for UniqueHandleMap<T> {
+    type Output = T;
+
+    fn index(&self, handle: Handle<T>) -> &Self::Output {
+        self.get(handle).expect("valid handle")
+    }
+}
+
+impl<T: Hash + Eq> UniqueHandleMap<T> {
+    pub fn new() -> Self {
+        Self {
+            values: DenseHandleMap::new(),
+            lookup: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of unique values in the map.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns a reference to the value associated with `handle`.
+    ///
+    /// Returns `None` if the value does not exist.
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.values.get(handle)
+    }
+
+    /// Returns the handle of `value` if an equal value has already been interned.
+    pub fn get_handle(&self, value: &T) -> Option<Handle<T>> {
+        self.lookup
+            .get(&hash_of(value))?
+            .iter()
+            .copied()
+            .find(|handle| self.values.get(*handle) == Some(value))
+    }
+
+    /// Interns `value`, returning its handle.
+    ///
+    /// If an equal value is already present, its existing handle is returned
+    /// and `value` is dropped instead of being stored again.
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        if let Some(handle) = self.get_handle(&value) {
+            return handle;
+        }
+
+        let hash = hash_of(&value);
+        let handle = self.values.insert(value);
+        self.lookup.entry(hash).or_default().push(handle);
+        handle
+    }
+
+    /// Returns an iterator that yields all value refrences in the map and their associated [`Handle`].
+    pub fn iter(&self) -> dense::Iter<T> {
+        self.values.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn insert() {
+        let mut map = UniqueHandleMap::new();
+        let handle = map.insert(42);
+        assert_eq!(map.get(handle), Some(&42));
+    }
+
+    #[test]
+    pub fn dedup_on_insert() {
+        let mut map = UniqueHandleMap::new();
+        let first = map.insert(42);
+        let second = map.insert(42);
+        assert_eq!(first, second);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    pub fn get_handle() {
+        let mut map = UniqueHandleMap::new();
+        let handle = map.insert(42);
+        assert_eq!(map.get_handle(&42), Some(handle));
+        assert_eq!(map.get_handle(&123), None);
+    }
+}